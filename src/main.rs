@@ -1,22 +1,173 @@
-use std::io;
-use std::io::prelude::*;
-use std::fs::File;
-use serde::{Serialize, Deserialize};
+use std::cell::Cell;
+use std::fs;
+use std::path::PathBuf;
+use serde::{Serialize, Deserialize, Deserializer, Serializer};
+
+use clap::{Parser as ClapParser, Subcommand, ValueEnum};
 
 use nom::{
     bytes::complete::take,
+    error::{context, Error as NomError, ErrorKind},
     number::complete::le_u32,
     multi::many_m_n,
     IResult,
     Parser,
 };
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+/// Known `WorldMap.dat` protocol revisions, keyed off the leading `version` field.
+///
+/// Revision changelog:
+/// - v1-v3: base layout. `chunk_pow` and `use_background` are present in every
+///   revision — the original unversioned parser read both unconditionally,
+///   which is our only evidence of their layout, so they are not gated here.
+/// - v4+: adds the appearance/variation condition fields to `WorldEventPage`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum WorldMapVersion {
+    V1,
+    V2,
+    V3,
+    V4,
+}
+
+impl WorldMapVersion {
+    fn from_raw(version: u32) -> Option<WorldMapVersion> {
+        match version {
+            1 => Some(WorldMapVersion::V1),
+            2 => Some(WorldMapVersion::V2),
+            3 => Some(WorldMapVersion::V3),
+            4 => Some(WorldMapVersion::V4),
+            _ => None,
+        }
+    }
+
+    fn has_event_page_conditions(self) -> bool {
+        self >= WorldMapVersion::V4
+    }
+}
+
+/// Reads the raw `version` field and resolves it to a known revision,
+/// returning both since `WorldMapFile::version` preserves the original number.
+fn world_map_version(input: &[u8]) -> IResult<&[u8], (u32, WorldMapVersion)> {
+    context("unknown WorldMap.dat version", |input| {
+        let (input, raw) = le_u32(input)?;
+        match WorldMapVersion::from_raw(raw) {
+            Some(version) => Ok((input, (raw, version))),
+            None => Err(nom::Err::Error(NomError::new(input, ErrorKind::Verify))),
+        }
+    })
+    .parse(input)
+}
+
+/// Text encoding used to decode `StdString` payloads for display/export.
+///
+/// The original editor is Japanese-origin, so the on-disk bytes are almost
+/// certainly Shift-JIS (CP932); that's the default here, with UTF-8 and
+/// Latin-1 available for files that turn out not to be.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "kebab-case")]
+enum TextEncoding {
+    #[default]
+    ShiftJis,
+    Utf8,
+    Latin1,
+}
+
+thread_local! {
+    /// Encoding used by `StdString`'s serde impl when decoding/encoding text.
+    /// Set once up front by the CLI before any (de)serialization happens.
+    static TEXT_ENCODING: Cell<TextEncoding> = const { Cell::new(TextEncoding::ShiftJis) };
+}
+
+fn set_text_encoding(encoding: TextEncoding) {
+    TEXT_ENCODING.with(|cell| cell.set(encoding));
+}
+
+fn current_text_encoding() -> TextEncoding {
+    TEXT_ENCODING.with(|cell| cell.get())
+}
+
+fn decode_text(data: &[u8], encoding: TextEncoding) -> String {
+    match encoding {
+        TextEncoding::ShiftJis => encoding_rs::SHIFT_JIS.decode(data).0.into_owned(),
+        TextEncoding::Utf8 => String::from_utf8_lossy(data).into_owned(),
+        TextEncoding::Latin1 => data.iter().map(|&b| b as char).collect(),
+    }
+}
+
+fn encode_text(text: &str, encoding: TextEncoding) -> Vec<u8> {
+    match encoding {
+        TextEncoding::ShiftJis => encoding_rs::SHIFT_JIS.encode(text).0.into_owned(),
+        TextEncoding::Utf8 => text.as_bytes().to_vec(),
+        TextEncoding::Latin1 => text.chars().map(|c| c as u32 as u8).collect(),
+    }
+}
+
+#[derive(Debug, Default, Clone)]
 struct StdString {
     length: u32,
     data: Vec<u8>,
 }
 
+impl StdString {
+    /// Decodes the raw bytes to text using the given encoding.
+    fn decoded_text(&self, encoding: TextEncoding) -> String {
+        decode_text(&self.data, encoding)
+    }
+}
+
+/// JSON representation of `StdString`: readable `text` plus the original
+/// `raw` bytes, kept only when re-encoding `text` wouldn't reproduce them
+/// losslessly (e.g. the bytes aren't valid in the configured encoding).
+#[derive(Serialize, Deserialize)]
+struct StdStringRepr {
+    length: u32,
+    text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    raw: Option<Vec<u8>>,
+}
+
+impl Serialize for StdString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let encoding = current_text_encoding();
+        let text = self.decoded_text(encoding);
+        let raw = if encode_text(&text, encoding) == self.data {
+            None
+        } else {
+            Some(self.data.clone())
+        };
+        StdStringRepr { length: self.length, text, raw }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for StdString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = StdStringRepr::deserialize(deserializer)?;
+        // `raw` only appears when `text` alone wouldn't reproduce the original
+        // bytes; that pairing was already validated at parse time, so trust
+        // it as-is. Otherwise `data` comes fresh from `text`, so `length`
+        // must be derived from it rather than the (possibly stale) `repr.length`
+        // -- except in the length<=1 "no data stored" case, where `length` 0
+        // and 1 are indistinguishable from `data` alone (both decode to the
+        // same empty text) and `repr.length` is the only place the original
+        // value survives; only treat it as ambiguous when it was already
+        // in that zone, so a genuine edit down to an empty/1-byte string
+        // still recomputes rather than keeping an unrelated stale length.
+        let (data, length) = match repr.raw {
+            Some(raw) => (raw, repr.length),
+            None => {
+                let data = encode_text(&repr.text, current_text_encoding());
+                let length = if data.len() <= 1 && repr.length <= 1 {
+                    repr.length
+                } else {
+                    data.len() as u32
+                };
+                (data, length)
+            }
+        };
+        Ok(StdString { length, data })
+    }
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 struct WorldChip {
     header: u32,
@@ -29,6 +180,21 @@ struct WorldChip {
     unused_string: StdString,
 }
 
+/// Appearance/variation condition fields, added to `WorldEventPage` in
+/// [`WorldMapVersion::V4`] and later.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct WorldEventPageConditions {
+    appearance_condition_world: u32, // 1
+    appearance_condition_variable: u32, // dropdown
+    appearance_condition_constant: u32, // spinner
+    appearance_condition_comparison_content: u32, // small dropdown
+    appearance_condition_total_score: u32,
+
+    variation_setting_present: u32,
+    variation_variable: u32,
+    variation_constant: u32,
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 struct WorldEventPage {
     start: u32,
@@ -40,15 +206,7 @@ struct WorldEventPage {
     play_after_clear: u32,
     on_game_clear: u32,
 
-    appearance_condition_world: u32, // 1
-    appearance_condition_variable: u32, // dropdown
-    appearance_condition_constant: u32, // spinner
-    appearance_condition_comparison_content: u32, // small dropdown
-    appearance_condition_total_score: u32,
-
-    variation_setting_present: u32,
-    variation_variable: u32,
-    variation_constant: u32,
+    conditions: Option<WorldEventPageConditions>,
 
     strings_count: u32, // 2 - std::vector<std::string>
 
@@ -95,7 +253,7 @@ struct WorldMapFile {
     world_chip_data: Vec<WorldChip>,
 
     tiles_count: u32,
-    map_chip_data: Vec<u32>,
+    map_chip_data: MapChipData,
 
     events_count: u32,
     event_data: Vec<WorldEventBase>,
@@ -133,16 +291,7 @@ fn world_chip(input: &[u8]) -> IResult<&[u8], WorldChip> {
     Ok((input, world_chip))
 }
 
-fn world_event_page(input: &[u8]) -> IResult<&[u8], WorldEventPage> {
-    let (input, start) = le_u32(input)?;
-    let (input, event_type) = le_u32(input)?;
-    let (input, graphic) = le_u32(input)?;
-
-    let (input, world_number) = le_u32(input)?;
-    let (input, pass_without_clear) = le_u32(input)?;
-    let (input, play_after_clear) = le_u32(input)?;
-    let (input, on_game_clear) = le_u32(input)?;
-
+fn world_event_page_conditions(input: &[u8]) -> IResult<&[u8], WorldEventPageConditions> {
     let (input, appearance_condition_world) = le_u32(input)?; // 1
     let (input, appearance_condition_variable) = le_u32(input)?; // dropdown
     let (input, appearance_condition_constant) = le_u32(input)?; // spinner
@@ -153,6 +302,37 @@ fn world_event_page(input: &[u8]) -> IResult<&[u8], WorldEventPage> {
     let (input, variation_variable) = le_u32(input)?;
     let (input, variation_constant) = le_u32(input)?;
 
+    let conditions = WorldEventPageConditions {
+        appearance_condition_world,
+        appearance_condition_variable,
+        appearance_condition_constant,
+        appearance_condition_comparison_content,
+        appearance_condition_total_score,
+        variation_setting_present,
+        variation_variable,
+        variation_constant,
+    };
+
+    Ok((input, conditions))
+}
+
+fn world_event_page(input: &[u8], version: WorldMapVersion) -> IResult<&[u8], WorldEventPage> {
+    let (input, start) = le_u32(input)?;
+    let (input, event_type) = le_u32(input)?;
+    let (input, graphic) = le_u32(input)?;
+
+    let (input, world_number) = le_u32(input)?;
+    let (input, pass_without_clear) = le_u32(input)?;
+    let (input, play_after_clear) = le_u32(input)?;
+    let (input, on_game_clear) = le_u32(input)?;
+
+    let (input, conditions) = if version.has_event_page_conditions() {
+        let (input, conditions) = world_event_page_conditions(input)?;
+        (input, Some(conditions))
+    } else {
+        (input, None)
+    };
+
     let (input, strings_count) = le_u32(input)?; // 2 - std::vector<std::string>
 
     let (input, world_name) = std_string(input)?; // std::string
@@ -166,14 +346,7 @@ fn world_event_page(input: &[u8]) -> IResult<&[u8], WorldEventPage> {
         pass_without_clear,
         play_after_clear,
         on_game_clear,
-        appearance_condition_world,
-        appearance_condition_variable,
-        appearance_condition_constant,
-        appearance_condition_comparison_content,
-        appearance_condition_total_score,
-        variation_setting_present,
-        variation_variable,
-        variation_constant,
+        conditions,
         strings_count,
         world_name,
         start_stage,
@@ -182,14 +355,16 @@ fn world_event_page(input: &[u8]) -> IResult<&[u8], WorldEventPage> {
     Ok((input, world_event_page))
 }
 
-fn world_event_base(input: &[u8]) -> IResult<&[u8], WorldEventBase> {
+fn world_event_base(input: &[u8], version: WorldMapVersion) -> IResult<&[u8], WorldEventBase> {
     let (input, header) = le_u32(input)?;
     let (input, placement_x) = le_u32(input)?;
     let (input, placement_y) = le_u32(input)?;
     let (input, strings_count) = le_u32(input)?;
     let (input, name) = std_string(input)?;
     let (input, pages_count) = le_u32(input)?;
-    let (input, pages) = many_m_n(0, pages_count.try_into().unwrap(), world_event_page).parse(input)?;
+    let (input, pages) = many_m_n(
+        0, pages_count.try_into().unwrap(), |i| world_event_page(i, version)
+    ).parse(input)?;
     let world_event_base = WorldEventBase {
         header,
         placement_x, placement_y,
@@ -199,8 +374,180 @@ fn world_event_base(input: &[u8]) -> IResult<&[u8], WorldEventBase> {
     Ok((input, world_event_base))
 }
 
+/// Run-length-encodes a flat tile list as `(tile, run_len)` pairs.
+fn rle_encode(flat: &[u32]) -> Vec<(u32, u32)> {
+    let mut runs: Vec<(u32, u32)> = Vec::new();
+    for &tile in flat {
+        match runs.last_mut() {
+            Some((last_tile, run_len)) if *last_tile == tile => *run_len += 1,
+            _ => runs.push((tile, 1)),
+        }
+    }
+    runs
+}
+
+/// Expands `(tile, run_len)` pairs back into the flat tile list `world_map` expects.
+fn rle_decode(runs: &[(u32, u32)]) -> Vec<u32> {
+    let mut flat = Vec::new();
+    for &(tile, run_len) in runs {
+        flat.extend(std::iter::repeat_n(tile, run_len as usize));
+    }
+    flat
+}
+
+thread_local! {
+    /// Whether `MapChipData`'s serde impl exports `map_chip_data` as
+    /// run-length-encoded pairs instead of the flat tile list. Set once up
+    /// front by the CLI; the on-disk `.dat` layout always stays flat.
+    static MAP_CHIP_RLE: Cell<bool> = const { Cell::new(false) };
+}
+
+fn set_map_chip_rle(enabled: bool) {
+    MAP_CHIP_RLE.with(|cell| cell.set(enabled));
+}
+
+fn map_chip_rle_enabled() -> bool {
+    MAP_CHIP_RLE.with(|cell| cell.get())
+}
+
+/// The flat tile list read from/written to `WorldMap.dat`. Its JSON
+/// `Serialize`/`Deserialize` impls optionally switch to a run-length-encoded
+/// `(tile, run_len)` form (see `set_map_chip_rle`) to shrink dumps of sparse
+/// maps; the binary layout is unaffected either way.
+#[derive(Debug, Default, Clone)]
+struct MapChipData(Vec<u32>);
+
+impl std::ops::Deref for MapChipData {
+    type Target = Vec<u32>;
+
+    fn deref(&self) -> &Vec<u32> {
+        &self.0
+    }
+}
+
+impl Serialize for MapChipData {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if map_chip_rle_enabled() {
+            rle_encode(&self.0).serialize(serializer)
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MapChipData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if map_chip_rle_enabled() {
+            let runs = Vec::<(u32, u32)>::deserialize(deserializer)?;
+            Ok(MapChipData(rle_decode(&runs)))
+        } else {
+            let flat = Vec::<u32>::deserialize(deserializer)?;
+            Ok(MapChipData(flat))
+        }
+    }
+}
+
+fn write_std_string(out: &mut Vec<u8>, value: &StdString) {
+    out.extend_from_slice(&value.length.to_le_bytes());
+    if value.length > 1 {
+        out.extend_from_slice(&value.data);
+    }
+}
+
+fn write_world_chip(out: &mut Vec<u8>, value: &WorldChip) {
+    out.extend_from_slice(&value.header.to_le_bytes());
+    out.extend_from_slice(&value.tile_index.to_le_bytes());
+    out.extend_from_slice(&value.locked.to_le_bytes());
+    out.extend_from_slice(&value.graphic.to_le_bytes());
+    out.extend_from_slice(&value.strings_count.to_le_bytes());
+    write_std_string(out, &value.name);
+    write_std_string(out, &value.unused_string);
+}
+
+fn write_world_event_page(out: &mut Vec<u8>, value: &WorldEventPage) {
+    out.extend_from_slice(&value.start.to_le_bytes());
+    out.extend_from_slice(&value.event_type.to_le_bytes());
+    out.extend_from_slice(&value.graphic.to_le_bytes());
+
+    out.extend_from_slice(&value.world_number.to_le_bytes());
+    out.extend_from_slice(&value.pass_without_clear.to_le_bytes());
+    out.extend_from_slice(&value.play_after_clear.to_le_bytes());
+    out.extend_from_slice(&value.on_game_clear.to_le_bytes());
+
+    if let Some(conditions) = &value.conditions {
+        out.extend_from_slice(&conditions.appearance_condition_world.to_le_bytes());
+        out.extend_from_slice(&conditions.appearance_condition_variable.to_le_bytes());
+        out.extend_from_slice(&conditions.appearance_condition_constant.to_le_bytes());
+        out.extend_from_slice(&conditions.appearance_condition_comparison_content.to_le_bytes());
+        out.extend_from_slice(&conditions.appearance_condition_total_score.to_le_bytes());
+
+        out.extend_from_slice(&conditions.variation_setting_present.to_le_bytes());
+        out.extend_from_slice(&conditions.variation_variable.to_le_bytes());
+        out.extend_from_slice(&conditions.variation_constant.to_le_bytes());
+    }
+
+    out.extend_from_slice(&value.strings_count.to_le_bytes());
+
+    write_std_string(out, &value.world_name);
+    write_std_string(out, &value.start_stage);
+}
+
+fn write_world_event_base(out: &mut Vec<u8>, value: &WorldEventBase) {
+    out.extend_from_slice(&value.header.to_le_bytes());
+    out.extend_from_slice(&value.placement_x.to_le_bytes());
+    out.extend_from_slice(&value.placement_y.to_le_bytes());
+    out.extend_from_slice(&value.strings_count.to_le_bytes());
+    write_std_string(out, &value.name);
+    out.extend_from_slice(&value.pages_count.to_le_bytes());
+    for page in &value.pages {
+        write_world_event_page(out, page);
+    }
+}
+
+/// Re-emits a `WorldMapFile` in the exact byte layout `world_map` reads,
+/// so `world_map(&write_world_map(file)) == (&[], file)` for any parsed file.
+fn write_world_map(value: &WorldMapFile) -> Vec<u8> {
+    let mut out = Vec::new();
+
+    out.extend_from_slice(&value.version.to_le_bytes());
+    out.extend_from_slice(&value.settings_count.to_le_bytes());
+    out.extend_from_slice(&value.horizontal_width.to_le_bytes());
+    out.extend_from_slice(&value.vertical_width.to_le_bytes());
+    out.extend_from_slice(&value.chunk_width.to_le_bytes());
+    out.extend_from_slice(&value.chunk_pow.to_le_bytes());
+    out.extend_from_slice(&value.initial_position_x.to_le_bytes());
+    out.extend_from_slice(&value.initial_position_y.to_le_bytes());
+    out.extend_from_slice(&value.background_index.to_le_bytes());
+    out.extend_from_slice(&value.use_background.to_le_bytes());
+    out.extend_from_slice(&value.strings_count.to_le_bytes());
+    write_std_string(&mut out, &value.name);
+    write_std_string(&mut out, &value.bg_path);
+
+    out.extend_from_slice(&value.tiles_types_count.to_le_bytes());
+    for chip in &value.world_chip_data {
+        write_world_chip(&mut out, chip);
+    }
+
+    out.extend_from_slice(&value.tiles_count.to_le_bytes());
+    for tile in value.map_chip_data.iter() {
+        out.extend_from_slice(&tile.to_le_bytes());
+    }
+
+    out.extend_from_slice(&value.events_count.to_le_bytes());
+    for event in &value.event_data {
+        write_world_event_base(&mut out, event);
+    }
+
+    out.extend_from_slice(&value.events_pal_count.to_le_bytes());
+    for event in &value.event_template_data {
+        write_world_event_base(&mut out, event);
+    }
+
+    out
+}
+
 fn world_map(input: &[u8]) -> IResult<&[u8], WorldMapFile> {
-    let (input, version) = le_u32(input)?;
+    let (input, (raw_version, version)) = world_map_version(input)?;
     let (input, settings_count) = le_u32(input)?;
     let (input, horizontal_width) = le_u32(input)?;
     let (input, vertical_width) = le_u32(input)?;
@@ -221,16 +568,18 @@ fn world_map(input: &[u8]) -> IResult<&[u8], WorldMapFile> {
     let (input, map_chip_data) = many_m_n(
         0, tiles_count.try_into().unwrap(), le_u32
     ).parse(input)?;
+    let map_chip_data = MapChipData(map_chip_data);
     let (input, events_count) = le_u32(input)?;
     let (input, event_data) = many_m_n(
-        0, tiles_count.try_into().unwrap(), world_event_base
+        0, events_count.try_into().unwrap(), |i| world_event_base(i, version)
     ).parse(input)?;
     let (input, events_pal_count) = le_u32(input)?;
     let (input, event_template_data) = many_m_n(
-        0, tiles_count.try_into().unwrap(), world_event_base
+        0, events_pal_count.try_into().unwrap(), |i| world_event_base(i, version)
     ).parse(input)?;
     let world_map_file = WorldMapFile {
-        version, settings_count,
+        version: raw_version,
+        settings_count,
         horizontal_width, vertical_width,
         chunk_width, chunk_pow,
         initial_position_x, initial_position_y,
@@ -244,13 +593,340 @@ fn world_map(input: &[u8]) -> IResult<&[u8], WorldMapFile> {
     Ok((input, world_map_file))
 }
 
-fn main() -> io::Result<()> {
-    let mut f = File::open("./WorldMap.dat")?;
-    let mut buf = Vec::new();
-    let _ = f.read_to_end(&mut buf)?;
+/// Whether `validate_world_map` stops at the first anomaly or collects all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "kebab-case")]
+enum ValidationMode {
+    /// Parse best-effort and return every anomaly found.
+    Lenient,
+    /// Fail on the first anomaly.
+    Strict,
+}
+
+/// A single anomaly surfaced by `validate_world_map`: a declared count,
+/// header, or stream length that disagrees with what was actually parsed,
+/// or (`ParseFailed`) a stream that `world_map` could not parse at all.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind")]
+enum ValidationIssue {
+    ParseFailed { message: String },
+    CountMismatch { path: String, declared: u32, actual: usize },
+    UnexpectedHeader { path: String, expected: u32, actual: u32 },
+    TrailingBytes { count: usize },
+}
+
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationIssue::ParseFailed { message } => write!(f, "parse failed: {message}"),
+            ValidationIssue::CountMismatch { path, declared, actual } => write!(
+                f, "{path}: declared count {declared} does not match {actual} element(s) actually present"
+            ),
+            ValidationIssue::UnexpectedHeader { path, expected, actual } => {
+                write!(f, "{path}: expected header magic {expected}, found {actual}")
+            }
+            ValidationIssue::TrailingBytes { count } => {
+                write!(f, "{count} trailing byte(s) after parsing world map")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationIssue {}
+
+fn check_count(issues: &mut Vec<ValidationIssue>, path: &str, declared: u32, actual: usize) {
+    if declared as usize != actual {
+        issues.push(ValidationIssue::CountMismatch {
+            path: path.to_string(),
+            declared,
+            actual,
+        });
+    }
+}
+
+fn check_header(issues: &mut Vec<ValidationIssue>, path: &str, header: u32) {
+    // All observed `header` fields are reserved/unused and expected to be zero.
+    if header != 0 {
+        issues.push(ValidationIssue::UnexpectedHeader {
+            path: path.to_string(),
+            expected: 0,
+            actual: header,
+        });
+    }
+}
+
+/// Parses `input` like `world_map`, but cross-checks every declared
+/// `*_count`/`header`/`strings_count` field against what was actually
+/// parsed instead of trusting it blindly.
+///
+/// In `Lenient` mode, parsing proceeds best-effort and every anomaly found
+/// is returned alongside the parsed file. In `Strict` mode, the first
+/// anomaly found is returned as an error instead. If `world_map` itself
+/// fails, a `ParseFailed` issue is returned as the error in either mode.
+fn validate_world_map(
+    input: &[u8],
+    mode: ValidationMode,
+) -> Result<(WorldMapFile, Vec<ValidationIssue>), ValidationIssue> {
+    let (remaining, world_map_file) = world_map(input)
+        .map_err(|err| ValidationIssue::ParseFailed { message: format!("{err}") })?;
+
+    let mut issues = Vec::new();
+
+    check_count(&mut issues, "tiles_types_count", world_map_file.tiles_types_count, world_map_file.world_chip_data.len());
+    check_count(&mut issues, "tiles_count", world_map_file.tiles_count, world_map_file.map_chip_data.len());
+    check_count(&mut issues, "events_count", world_map_file.events_count, world_map_file.event_data.len());
+    check_count(&mut issues, "events_pal_count", world_map_file.events_pal_count, world_map_file.event_template_data.len());
+    check_count(&mut issues, "strings_count", world_map_file.strings_count, 2);
+
+    for (i, chip) in world_map_file.world_chip_data.iter().enumerate() {
+        let path = format!("world_chip_data[{i}]");
+        check_header(&mut issues, &format!("{path}.header"), chip.header);
+        check_count(&mut issues, &format!("{path}.strings_count"), chip.strings_count, 2);
+    }
+
+    for (list_name, events) in [
+        ("event_data", &world_map_file.event_data),
+        ("event_template_data", &world_map_file.event_template_data),
+    ] {
+        for (i, event) in events.iter().enumerate() {
+            let path = format!("{list_name}[{i}]");
+            check_header(&mut issues, &format!("{path}.header"), event.header);
+            check_count(&mut issues, &format!("{path}.strings_count"), event.strings_count, 1);
+            check_count(&mut issues, &format!("{path}.pages_count"), event.pages_count, event.pages.len());
+            for (j, page) in event.pages.iter().enumerate() {
+                check_count(&mut issues, &format!("{path}.pages[{j}].strings_count"), page.strings_count, 2);
+            }
+        }
+    }
+
+    if !remaining.is_empty() {
+        issues.push(ValidationIssue::TrailingBytes { count: remaining.len() });
+    }
+
+    if mode == ValidationMode::Strict && !issues.is_empty() {
+        return Err(issues.remove(0));
+    }
+
+    Ok((world_map_file, issues))
+}
 
-    println!("{:#?}", world_map(&buf));
-    // println!("{:#?}", world_map(b"\xFC\x03\x00\x00\x08\x00\x00\x00\x14\x00\x00\x00\x0F\x00\x00\x00\x20\x00\x00\x00\x05\x00\x00\x00\x09\x00\x00\x00\x07\x00\x00\x00"));
+/// Parses a `WorldMap.dat`, rejecting any trailing unconsumed bytes.
+fn parse_world_map_file(bytes: &[u8]) -> Result<WorldMapFile, String> {
+    let (remaining, world_map_file) = world_map(bytes)
+        .map_err(|err| format!("failed to parse world map: {err}"))?;
+    if !remaining.is_empty() {
+        return Err(format!("{} trailing byte(s) after parsing world map", remaining.len()));
+    }
+    Ok(world_map_file)
+}
+
+#[derive(ClapParser)]
+#[command(name = "ae4_worldmap_parser", about = "Parse and rebuild ae4 WorldMap.dat files")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Parse a WorldMap.dat and print it as JSON
+    Dump {
+        file: PathBuf,
+        /// Encoding used to decode StdString payloads to text
+        #[arg(long, value_enum, default_value_t = TextEncoding::default())]
+        encoding: TextEncoding,
+        /// Export map_chip_data as run-length-encoded (tile, run_len) pairs
+        #[arg(long)]
+        rle: bool,
+    },
+    /// Deserialize a JSON dump and write it back out as a WorldMap.dat
+    Build {
+        json: PathBuf,
+        out: PathBuf,
+        /// Encoding used to re-encode StdString text back to bytes
+        #[arg(long, value_enum, default_value_t = TextEncoding::default())]
+        encoding: TextEncoding,
+        /// Read map_chip_data as run-length-encoded (tile, run_len) pairs
+        #[arg(long)]
+        rle: bool,
+    },
+    /// Parse a WorldMap.dat, re-serialize it, and assert the bytes match
+    Verify {
+        file: PathBuf,
+    },
+    /// Parse a WorldMap.dat and report any count/header anomalies
+    Check {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = ValidationMode::Lenient)]
+        mode: ValidationMode,
+    },
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Command::Dump { file, encoding, rle } => {
+            set_text_encoding(encoding);
+            set_map_chip_rle(rle);
+            let bytes = fs::read(&file)?;
+            let world_map_file = parse_world_map_file(&bytes)?;
+            println!("{}", serde_json::to_string_pretty(&world_map_file)?);
+        }
+        Command::Build { json, out, encoding, rle } => {
+            set_text_encoding(encoding);
+            set_map_chip_rle(rle);
+            let json_text = fs::read_to_string(&json)?;
+            let world_map_file: WorldMapFile = serde_json::from_str(&json_text)?;
+            fs::write(&out, write_world_map(&world_map_file))?;
+        }
+        Command::Verify { file } => {
+            let bytes = fs::read(&file)?;
+            let world_map_file = parse_world_map_file(&bytes)?;
+            let round_tripped = write_world_map(&world_map_file);
+            if round_tripped == bytes {
+                println!("OK: {} round-trips byte-identical", file.display());
+            } else {
+                return Err(format!(
+                    "{} does not round-trip: {} bytes parsed, {} bytes written",
+                    file.display(),
+                    bytes.len(),
+                    round_tripped.len()
+                )
+                .into());
+            }
+        }
+        Command::Check { file, mode } => {
+            let bytes = fs::read(&file)?;
+            let (_, issues) = validate_world_map(&bytes, mode)?;
+            if issues.is_empty() {
+                println!("OK: no anomalies found in {}", file.display());
+            } else {
+                for issue in &issues {
+                    println!("{issue}");
+                }
+                return Err(format!("{} anomal{} found in {}", issues.len(), if issues.len() == 1 { "y" } else { "ies" }, file.display()).into());
+            }
+        }
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_world_map_round_trips_minimal_file() {
+        let u32s: &[u32] = &[
+            1, // version
+            0, // settings_count
+            0, 0, // horizontal_width, vertical_width
+            0, 0, // chunk_width, chunk_pow
+            0, 0, // initial_position_x, initial_position_y
+            0, 0, // background_index, use_background
+            2, // strings_count
+            0, // name.length (empty StdString)
+            0, // bg_path.length (empty StdString)
+            0, // tiles_types_count
+            0, // tiles_count
+            0, // events_count
+            0, // events_pal_count
+        ];
+        let bytes: Vec<u8> = u32s.iter().flat_map(|n| n.to_le_bytes()).collect();
+
+        let (remaining, parsed) = world_map(&bytes).expect("minimal file should parse");
+        assert!(remaining.is_empty());
+        assert_eq!(write_world_map(&parsed), bytes);
+    }
+
+    #[test]
+    fn std_string_json_round_trip_preserves_length_0_and_1() {
+        for length in [0u32, 1u32] {
+            let original = StdString { length, data: Vec::new() };
+            let json = serde_json::to_string(&original).unwrap();
+            let parsed: StdString = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed.length, length);
+            assert_eq!(parsed.data, original.data);
+        }
+    }
+
+    #[test]
+    fn std_string_json_round_trip_preserves_real_text() {
+        let original = StdString { length: 4, data: b"map1".to_vec() };
+        let json = serde_json::to_string(&original).unwrap();
+        let parsed: StdString = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.length, original.length);
+        assert_eq!(parsed.data, original.data);
+    }
+
+    #[test]
+    fn from_raw_accepts_known_versions_and_rejects_unknown() {
+        assert_eq!(WorldMapVersion::from_raw(1), Some(WorldMapVersion::V1));
+        assert_eq!(WorldMapVersion::from_raw(2), Some(WorldMapVersion::V2));
+        assert_eq!(WorldMapVersion::from_raw(3), Some(WorldMapVersion::V3));
+        assert_eq!(WorldMapVersion::from_raw(4), Some(WorldMapVersion::V4));
+        assert_eq!(WorldMapVersion::from_raw(99), None);
+    }
+
+    #[test]
+    fn has_event_page_conditions_is_gated_at_v4() {
+        assert!(!WorldMapVersion::V3.has_event_page_conditions());
+        assert!(WorldMapVersion::V4.has_event_page_conditions());
+    }
+
+    #[test]
+    fn world_map_version_rejects_unknown_version() {
+        let bytes = 99u32.to_le_bytes();
+        assert!(world_map_version(&bytes).is_err());
+    }
+
+    #[test]
+    fn validate_world_map_reports_count_mismatch_in_lenient_and_errors_in_strict() {
+        let u32s: &[u32] = &[
+            1, // version
+            0, // settings_count
+            0, 0, // horizontal_width, vertical_width
+            0, 0, // chunk_width, chunk_pow
+            0, 0, // initial_position_x, initial_position_y
+            0, 0, // background_index, use_background
+            2, // strings_count
+            0, // name.length
+            0, // bg_path.length
+            2, // tiles_types_count: declared 2, but no chip data actually follows
+            0, // tiles_count
+            0, // events_count
+            0, // events_pal_count
+        ];
+        let bytes: Vec<u8> = u32s.iter().flat_map(|n| n.to_le_bytes()).collect();
+
+        let (parsed, issues) = validate_world_map(&bytes, ValidationMode::Lenient)
+            .expect("lenient mode still returns the best-effort parse");
+        assert!(parsed.world_chip_data.is_empty());
+        assert!(issues.iter().any(|issue| matches!(
+            issue,
+            ValidationIssue::CountMismatch { path, declared: 2, actual: 0 } if path == "tiles_types_count"
+        )));
+
+        let err = validate_world_map(&bytes, ValidationMode::Strict)
+            .expect_err("strict mode fails on the same mismatch");
+        assert!(matches!(err, ValidationIssue::CountMismatch { .. }));
+    }
+
+    #[test]
+    fn rle_round_trips_varied_runs() {
+        let flat = vec![1, 1, 1, 2, 2, 3, 4, 4, 4, 4];
+        let runs = rle_encode(&flat);
+        assert_eq!(runs, vec![(1, 3), (2, 2), (3, 1), (4, 4)]);
+        assert_eq!(rle_decode(&runs), flat);
+    }
+
+    #[test]
+    fn rle_round_trips_empty_input() {
+        let flat: Vec<u32> = Vec::new();
+        let runs = rle_encode(&flat);
+        assert!(runs.is_empty());
+        assert_eq!(rle_decode(&runs), flat);
+    }
+}